@@ -0,0 +1,182 @@
+/*
+ * Outbound commands the host can send back to the transmitter, e.g. to
+ * reboot it into its USB bootloader or reset its tuning to defaults
+ * without unplugging it.
+ */
+
+use std::io::{self, Write};
+
+/// A command the host can send to the transmitter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Commands {
+    /// Reset the transmitter's tuning (sensitivity, deadzone, etc) to its defaults
+    SetDefaults,
+    /// Tell the transmitter to start streaming packets, if it isn't already
+    EnableStreaming,
+    /// Reboot the transmitter into its USB bootloader, e.g. to reflash an
+    /// RP2040-style device without unplugging it
+    RebootToBootloader,
+}
+
+impl Commands {
+    /// Every command, in menu order
+    pub const ALL: [Self; 3] = [
+        Self::SetDefaults,
+        Self::EnableStreaming,
+        Self::RebootToBootloader,
+    ];
+
+    /// The single opcode byte identifying this command on the wire
+    const fn opcode(self) -> u8 {
+        match self {
+            Self::SetDefaults => 0x01,
+            Self::EnableStreaming => 0x02,
+            Self::RebootToBootloader => 0x03,
+        }
+    }
+
+    /// The command an opcode byte identifies, if any
+    const fn from_opcode(opcode: u8) -> Option<Self> {
+        match opcode {
+            0x01 => Some(Self::SetDefaults),
+            0x02 => Some(Self::EnableStreaming),
+            0x03 => Some(Self::RebootToBootloader),
+            _ => None,
+        }
+    }
+
+    /// A human-readable label for use in the command menu
+    #[must_use]
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::SetDefaults => "reset to defaults",
+            Self::EnableStreaming => "enable streaming",
+            Self::RebootToBootloader => "reboot to bootloader",
+        }
+    }
+
+    /// Write this command to `writer`, framed as a header byte, the
+    /// opcode, and its bitwise complement as a simple checksum. The
+    /// transmitter echoes this same framing back, opcode for opcode, to
+    /// acknowledge it; see [`AckReader`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing to or flushing `writer` fails.
+    pub fn send(self, writer: &mut impl Write) -> io::Result<()> {
+        let opcode = self.opcode();
+        writer.write_all(&[HEADER, opcode, !opcode])?;
+        writer.flush()
+    }
+}
+
+/// The header byte that marks the start of a command frame, both the ones
+/// `Commands::send` writes and the acknowledgement frames the transmitter
+/// echoes back once it's executed one
+pub const HEADER: u8 = 0xAA;
+
+/// What [`AckReader::feed`] decided about the bytes fed to it so far
+#[derive(Debug)]
+pub enum AckEvent {
+    /// Still waiting on more bytes to decide
+    Pending,
+    /// A complete, checksum-valid acknowledgement for `Commands`
+    Acked(Commands),
+    /// Not an acknowledgement frame after all (bad header, opcode, or
+    /// checksum); these buffered bytes should be replayed through the
+    /// motion protocol decoder instead, since they were never consumed
+    NotAck(Vec<u8>),
+}
+
+/// Watches the incoming byte stream for a command acknowledgement frame
+/// (the same `[HEADER, opcode, !opcode]` framing `Commands::send` writes)
+/// interleaved with ordinary motion packets from the active
+/// `MouseProtocol`, the same way the motion protocols themselves
+/// resynchronise on their own header byte after a framing error.
+#[derive(Debug, Default)]
+pub struct AckReader {
+    /// Bytes tentatively buffered since we saw `HEADER`
+    pending: Vec<u8>,
+}
+
+impl AckReader {
+    /// Feed a single byte read from the serial port
+    pub fn feed(&mut self, byte: u8) -> AckEvent {
+        if self.pending.is_empty() {
+            if byte == HEADER {
+                self.pending.push(byte);
+                return AckEvent::Pending;
+            }
+            return AckEvent::NotAck(vec![byte]);
+        }
+        self.pending.push(byte);
+        if self.pending.len() < 3 {
+            return AckEvent::Pending;
+        }
+        let frame = std::mem::take(&mut self.pending);
+        let (opcode, complement) = (frame[1], frame[2]);
+        if complement == !opcode {
+            if let Some(command) = Commands::from_opcode(opcode) {
+                return AckEvent::Acked(command);
+            }
+        }
+        AckEvent::NotAck(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AckEvent, AckReader, Commands, HEADER};
+
+    #[test]
+    fn roundtrips_a_command_through_send() {
+        let mut frame = Vec::new();
+        Commands::RebootToBootloader.send(&mut frame).unwrap();
+
+        let mut reader = AckReader::default();
+        let (&last, rest) = frame.split_last().unwrap();
+        for &byte in rest {
+            assert!(matches!(reader.feed(byte), AckEvent::Pending));
+        }
+        assert!(matches!(
+            reader.feed(last),
+            AckEvent::Acked(Commands::RebootToBootloader)
+        ));
+    }
+
+    #[test]
+    fn checksum_mismatch_falls_through_to_not_ack_with_bytes_intact() {
+        let mut reader = AckReader::default();
+        // A valid header and opcode, but a complement that doesn't match
+        let frame = [HEADER, Commands::SetDefaults.opcode(), 0x00];
+        assert!(matches!(reader.feed(frame[0]), AckEvent::Pending));
+        assert!(matches!(reader.feed(frame[1]), AckEvent::Pending));
+        match reader.feed(frame[2]) {
+            AckEvent::NotAck(bytes) => assert_eq!(bytes, frame),
+            other => panic!("expected NotAck, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn resyncs_after_a_stray_header_byte() {
+        let mut reader = AckReader::default();
+        // A stray header followed by two bytes that don't form a valid
+        // frame (checksum doesn't match) should be handed back as a whole
+        // in `NotAck`, leaving the reader ready to sync on the next real
+        // header
+        assert!(matches!(reader.feed(HEADER), AckEvent::Pending));
+        assert!(matches!(reader.feed(0x00), AckEvent::Pending));
+        assert!(matches!(reader.feed(0x00), AckEvent::NotAck(_)));
+
+        let mut frame = Vec::new();
+        Commands::EnableStreaming.send(&mut frame).unwrap();
+        let (&last, rest) = frame.split_last().unwrap();
+        for &byte in rest {
+            assert!(matches!(reader.feed(byte), AckEvent::Pending));
+        }
+        assert!(matches!(
+            reader.feed(last),
+            AckEvent::Acked(Commands::EnableStreaming)
+        ));
+    }
+}
@@ -0,0 +1,272 @@
+/*
+ * Optional GTK control panel, behind the `gui` cargo feature.
+ *
+ * Replaces the one-shot `inquire::Select` prompts with a window offering a
+ * port/baud picker, a Connect/Disconnect/Reconnect button, and a scrolling
+ * log of decoded packets. The serial read loop runs on a worker thread and
+ * pushes parsed events back to the UI thread over a `glib` channel, with a
+ * shared `AtomicBool` connection flag so a disconnected port can be
+ * recovered from instead of panicking the whole program.
+ */
+
+use std::{
+    io::{ErrorKind, Read},
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use gtk::{glib, prelude::*};
+use mouse_rs::Mouse;
+use serialport::available_ports;
+
+use crate::{
+    motion::MotionProfile,
+    protocol::{LineProtocol, MouseProtocol},
+};
+
+/// A message sent from the serial worker thread to the UI thread
+enum Message {
+    /// A line to append to the log view
+    Log(String),
+    /// The port dropped out (unplugged, read error, etc); the UI should
+    /// fall back to the disconnected state
+    Disconnected,
+}
+
+/// Launch the GTK control panel. Blocks until the window is closed.
+pub fn run() {
+    let app = gtk::Application::builder()
+        .application_id("io.github.circles-png.micro-morse-mouse")
+        .build();
+    app.connect_activate(build_ui);
+    app.run();
+}
+
+/// Build and show the main window
+fn build_ui(app: &gtk::Application) {
+    // Shared flag so the Connect button's handler and the worker thread
+    // agree on whether a port is currently open
+    let connected = Arc::new(AtomicBool::new(false));
+
+    let port_picker = gtk::ComboBoxText::new();
+    for port in available_ports().unwrap_or_default() {
+        port_picker.append_text(&port.port_name);
+    }
+    port_picker.set_active(Some(0));
+
+    let baud_picker = gtk::ComboBoxText::new();
+    for baud in ["9600", "19200", "38400", "57600", "115200"] {
+        baud_picker.append_text(baud);
+    }
+    baud_picker.set_active(Some(4));
+
+    let connect_button = gtk::Button::with_label("Connect");
+    let disconnect_button = gtk::Button::with_label("Disconnect");
+    let reconnect_button = gtk::Button::with_label("Reconnect");
+    disconnect_button.set_sensitive(false);
+    reconnect_button.set_sensitive(false);
+
+    let log = gtk::TextView::new();
+    log.set_editable(false);
+    log.set_monospace(true);
+    let log_scroller = gtk::ScrolledWindow::builder()
+        .child(&log)
+        .vexpand(true)
+        .build();
+
+    let controls = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+    controls.append(&port_picker);
+    controls.append(&baud_picker);
+    controls.append(&connect_button);
+    controls.append(&disconnect_button);
+    controls.append(&reconnect_button);
+
+    let layout = gtk::Box::new(gtk::Orientation::Vertical, 6);
+    layout.append(&controls);
+    layout.append(&log_scroller);
+
+    let window = gtk::ApplicationWindow::builder()
+        .application(app)
+        .title("Joystick Mouse Controller")
+        .default_width(480)
+        .default_height(320)
+        .child(&layout)
+        .build();
+
+    // Drain worker-thread messages on the UI thread and append them to the log
+    let (sender, receiver) = glib::MainContext::channel(glib::Priority::DEFAULT);
+    let log_buffer = log.buffer();
+    receiver.attach(None, {
+        let connected = Arc::clone(&connected);
+        let connect_button = connect_button.clone();
+        let disconnect_button = disconnect_button.clone();
+        let reconnect_button = reconnect_button.clone();
+        move |message| {
+            match message {
+                Message::Log(line) => {
+                    log_buffer.insert(&mut log_buffer.end_iter(), &format!("{line}\n"));
+                }
+                Message::Disconnected => {
+                    connected.store(false, Ordering::SeqCst);
+                    connect_button.set_sensitive(true);
+                    disconnect_button.set_sensitive(false);
+                    reconnect_button.set_sensitive(true);
+                }
+            }
+            glib::ControlFlow::Continue
+        }
+    });
+
+    // The port handle, shared with the worker thread so Disconnect can
+    // drop it without tearing down the whole program
+    let port: Arc<Mutex<Option<Box<dyn serialport::SerialPort>>>> = Arc::new(Mutex::new(None));
+    // The previous worker thread's handle, so a new call to `start` can
+    // join it before opening a new port; without this, connecting again
+    // right after Disconnect could spawn a second reader while the first
+    // one is still finishing its last read
+    let worker: Arc<Mutex<Option<thread::JoinHandle<()>>>> = Arc::new(Mutex::new(None));
+
+    // Wrapped in `Rc` so both the Connect and Reconnect buttons can share
+    // it rather than each needing their own copy of the connect logic
+    let start: Rc<dyn Fn()> = Rc::new({
+        let connected = Arc::clone(&connected);
+        let port = Arc::clone(&port);
+        let worker = Arc::clone(&worker);
+        let sender = sender.clone();
+        let port_picker = port_picker.clone();
+        let baud_picker = baud_picker.clone();
+        let connect_button = connect_button.clone();
+        let disconnect_button = disconnect_button.clone();
+        let reconnect_button = reconnect_button.clone();
+        move || {
+            // Already connected: Connect is insensitive once that's true,
+            // but Reconnect stays clickable, so guard here too rather than
+            // opening a second handle the existing worker is still reading
+            if connected.load(Ordering::SeqCst) {
+                return;
+            }
+            let Some(port_name) = port_picker.active_text() else {
+                return;
+            };
+            let Some(baud) = baud_picker
+                .active_text()
+                .and_then(|text| text.parse::<u32>().ok())
+            else {
+                return;
+            };
+            // Join the previous worker, if any, before opening a new
+            // handle. It's already seen `connected` go false (by
+            // Disconnect, or by its own read erroring out) so this returns
+            // promptly rather than blocking the UI thread for long
+            if let Some(previous) = worker.lock().unwrap().take() {
+                let _ = previous.join();
+            }
+            // A short timeout, rather than `Duration::MAX`, so the read
+            // loop below re-checks `connected` promptly after Disconnect
+            // instead of staying blocked on an idle port indefinitely
+            let Ok(handle) = serialport::new(port_name, baud)
+                .timeout(Duration::from_millis(200))
+                .open()
+            else {
+                sender
+                    .send(Message::Log("failed to open port".to_owned()))
+                    .unwrap();
+                return;
+            };
+            // Keep the one handle the worker thread actually reads from in
+            // the shared `Mutex`, so Disconnect drops the same file
+            // descriptor the read loop is blocked on rather than an
+            // independent clone of it
+            *port.lock().unwrap() = Some(handle);
+            connected.store(true, Ordering::SeqCst);
+            connect_button.set_sensitive(false);
+            disconnect_button.set_sensitive(true);
+            reconnect_button.set_sensitive(true);
+
+            // The read loop runs here, on a background thread, so the UI
+            // never blocks on the serial port
+            let connected = Arc::clone(&connected);
+            let port = Arc::clone(&port);
+            let sender = sender.clone();
+            let handle = thread::spawn(move || {
+                let mouse = Mouse::new();
+                let mut protocol = LineProtocol::default();
+                let profile = MotionProfile::default();
+                let mut byte = [0_u8; 1];
+                while connected.load(Ordering::SeqCst) {
+                    // Borrow the shared handle just long enough to read one
+                    // byte, so Disconnect clearing the `Mutex` actually
+                    // closes the port this loop is using
+                    let read = port
+                        .lock()
+                        .unwrap()
+                        .as_mut()
+                        .map(|handle| handle.read_exact(&mut byte));
+                    match read {
+                        Some(Ok(())) => {}
+                        // Timed out with no data: loop back around to
+                        // re-check `connected` rather than treating it as
+                        // a disconnect
+                        Some(Err(error)) if error.kind() == ErrorKind::TimedOut => continue,
+                        // Any other error, or the handle having been taken
+                        // away by Disconnect, means there's nothing left
+                        // to read from
+                        Some(Err(_)) | None => break,
+                    }
+                    let Some(event) = protocol.feed(byte[0]) else {
+                        continue;
+                    };
+                    let (dx, dy) = profile.apply(event.dx, event.dy);
+                    if let Ok(position) = mouse.get_position() {
+                        let _ = mouse.move_to(position.x + dx, position.y + dy);
+                    }
+                    if let Some(click) = event.click() {
+                        let _ = mouse.click(&click);
+                    }
+                    let _ = mouse.wheel(event.scroll);
+                    sender
+                        .send(Message::Log(format!(
+                            "left={} right={} middle={} dx={dx} dy={dy} scroll={}",
+                            event.left, event.right, event.middle, event.scroll
+                        )))
+                        .unwrap();
+                }
+                connected.store(false, Ordering::SeqCst);
+                sender.send(Message::Disconnected).unwrap();
+            });
+            *worker.lock().unwrap() = Some(handle);
+        }
+    });
+
+    connect_button.connect_clicked({
+        let start = Rc::clone(&start);
+        move |_| start()
+    });
+    reconnect_button.connect_clicked({
+        let start = Rc::clone(&start);
+        move |_| start()
+    });
+    disconnect_button.connect_clicked({
+        let connected = Arc::clone(&connected);
+        let port = Arc::clone(&port);
+        let connect_button = connect_button.clone();
+        let reconnect_button = reconnect_button.clone();
+        move |disconnect_button| {
+            connected.store(false, Ordering::SeqCst);
+            // Dropping the port handle closes it; the worker thread's next
+            // read will see the handle gone (or error out) and send
+            // `Message::Disconnected`
+            *port.lock().unwrap() = None;
+            connect_button.set_sensitive(true);
+            disconnect_button.set_sensitive(false);
+            reconnect_button.set_sensitive(true);
+        }
+    });
+
+    window.present();
+}
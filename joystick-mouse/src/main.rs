@@ -9,21 +9,49 @@
 // Warn on clippy::nursery and clippy::pedantic
 #![warn(clippy::nursery, clippy::pedantic)]
 
+mod commands;
+#[cfg(feature = "gui")]
+mod gui;
+mod motion;
+mod protocol;
+
 // Import stuff from the standard library to read from and write to the serial port
 use std::{
-    io::{BufRead, BufReader},
+    io::Read,
+    thread,
     time::Duration, // Duration is used to set the timeout for the serial port
 };
 
-// `inquire::Select` is used to prompt the user to select a serial port
-use inquire::Select;
+// `inquire::Select` is used to prompt the user to select a serial port and
+// protocol; `CustomType` is used to let the user tweak the motion profile
+use inquire::{CustomType, Select};
 // `mouse_rs` is used to move the mouse
-use mouse_rs::{types::keys::Keys, Mouse};
+use mouse_rs::Mouse;
 // `serialport` is used to interact with the serial port
 use serialport::{available_ports, new};
 
+// Pull in the outbound command enum and the ack-frame detector
+use commands::{AckEvent, AckReader, Commands};
+// Pull in the acceleration curve
+use motion::MotionProfile;
+// Pull in the decoder trait and its implementations
+use protocol::{LineProtocol, MicrosoftProtocol, MouseProtocol, MousesystemsProtocol, Ps2Protocol};
+
 // Program entry point
+//
+// This is a straight-line sequence of prompts, a port open, and the
+// reader/writer split; splitting it into helpers would just scatter that
+// single flow across several one-call functions without adding clarity
+#[allow(clippy::too_many_lines)]
 fn main() {
+    // With the `gui` feature enabled, hand off to the GTK control panel
+    // instead of the `inquire` prompt flow below
+    #[cfg(feature = "gui")]
+    {
+        gui::run();
+        return;
+    }
+
     // Get the list of available serial ports
     let ports = available_ports().unwrap();
     // Prompt the user to select a serial port
@@ -33,83 +61,126 @@ fn main() {
     )
     .prompt()
     .unwrap();
+    // Prompt the user to select which wire protocol the transmitter speaks.
+    // The prompt's options are built from each protocol's own `name()`
+    // rather than a second, hand-kept list of display names.
+    let protocols: Vec<Box<dyn MouseProtocol>> = vec![
+        Box::new(LineProtocol::default()),
+        Box::new(MicrosoftProtocol::default()),
+        Box::new(MousesystemsProtocol::default()),
+        Box::new(Ps2Protocol::default()),
+    ];
+    let protocol_names = protocols.iter().map(|protocol| protocol.name()).collect();
+    let choice = Select::new("select a protocol", protocol_names).prompt().unwrap();
+    let mut protocol = protocols
+        .into_iter()
+        .find(|protocol| protocol.name() == choice)
+        .unwrap();
+    // Prompt the user to either use the default motion profile or
+    // calibrate each field, so users with different joystick hardware can
+    // tune feel without recompiling
+    let default_profile = MotionProfile::default();
+    let profile = match Select::new(
+        "motion profile",
+        vec!["default (linear, deadzone 40)", "calibrate"],
+    )
+    .prompt()
+    .unwrap()
+    {
+        "calibrate" => MotionProfile {
+            deadzone: CustomType::<f64>::new("deadzone radius")
+                .with_default(default_profile.deadzone)
+                .prompt()
+                .unwrap(),
+            max_input: CustomType::<f64>::new("max input magnitude")
+                .with_default(default_profile.max_input)
+                .prompt()
+                .unwrap(),
+            gain_x: CustomType::<f64>::new("x gain")
+                .with_default(default_profile.gain_x)
+                .prompt()
+                .unwrap(),
+            gain_y: CustomType::<f64>::new("y gain")
+                .with_default(default_profile.gain_y)
+                .prompt()
+                .unwrap(),
+            exponent: CustomType::<f64>::new("curve exponent")
+                .with_default(default_profile.exponent)
+                .prompt()
+                .unwrap(),
+        },
+        _ => default_profile,
+    };
     // Open the serial port
     let port = new(selected_port, 115_200)
         .timeout(Duration::MAX) // With a timeout of `Duration::MAX`
         .open()
         .unwrap();
-    // Create a buffered reader to read from the serial port
-    let mut reader = BufReader::new(port);
-    // Create a new mouse object
-    let mouse = Mouse::new();
-    // Loop forever
-    loop {
-        // Create a new buffer to store the data
-        let mut buffer = Vec::new();
-        // Read until a `\r` is found
-        reader.read_until(b'\r', &mut buffer).unwrap();
-        // Convert the data to a string
-        let data = String::from_utf8(buffer);
-        // If the data is invalid, skip this iteration
-        let data = match data {
-            Ok(data) => data.to_string(),
-            Err(_) => continue,
-        };
-
-        // Split the data into a vector of numbers
-        let mut data = data
-            .split_whitespace()
-            .map(|number| number.parse::<i32>().unwrap());
+    // Clone the handle so the reader and the command writer can each own
+    // one side of the same underlying port
+    let mut writer = port.try_clone().unwrap();
+    let mut reader = port;
+    // Run the blocking read loop on its own thread so the main thread is
+    // free to prompt for outbound commands
+    thread::spawn(move || {
+        // Create a new mouse object
+        let mouse = Mouse::new();
+        // Watches for command acknowledgement frames interleaved with
+        // ordinary motion packets, since both share the same wire
+        let mut ack_reader = AckReader::default();
+        // Loop forever, reading one byte at a time so the protocol can
+        // resynchronise on its own header/sync marker after a framing
+        // error instead of discarding a whole line
+        let mut byte = [0_u8; 1];
+        loop {
+            reader.read_exact(&mut byte).unwrap();
+            // First check whether this byte is (part of) a command
+            // acknowledgement; if it turns out not to be, the buffered
+            // bytes are replayed through the motion protocol decoder below
+            // since they were never actually consumed
+            let bytes = match ack_reader.feed(byte[0]) {
+                AckEvent::Pending => continue,
+                AckEvent::Acked(command) => {
+                    eprintln!("transmitter acknowledged: {}", command.label());
+                    continue;
+                }
+                AckEvent::NotAck(bytes) => bytes,
+            };
+            for byte in bytes {
+                // Feed the byte to the decoder; skip this iteration if it
+                // hasn't assembled a full packet yet
+                let Some(event) = protocol.feed(byte) else {
+                    continue;
+                };
 
-        // If there are not 7 numbers, skip this iteration
-        if data.clone().count() != 7 {
-            continue;
-        }
-        // Decode the click state
-        let click = match (data.next().unwrap() == 1, data.next().unwrap() == 1) {
-            (false, false) => None,
-            (_, true) => Some(Keys::RIGHT),
-            (true, _) => Some(Keys::LEFT),
-        };
-        // Decode the mouse movement
-        let x = {
-            let mut x = (data.next().unwrap() - 512).clamp(-511, 511);
-            if x.abs() < 40 { // Check for the deadzone (square with side length `40 * 2` centered at the origin `(0, 0)`)
-                x = 0;
+                // Get the original mouse position
+                let position = mouse.get_position().unwrap();
+                // Apply the deadzone and acceleration curve, then move the
+                // mouse by the resulting amount
+                let (dx, dy) = profile.apply(event.dx, event.dy);
+                mouse
+                    .move_to(position.x + dx, position.y + dy)
+                    .unwrap();
+                // Click and scroll the mouse
+                if let Some(click) = event.click() {
+                    mouse.click(&click).unwrap();
+                }
+                mouse.wheel(event.scroll).unwrap();
             }
-            x
-        };
-        let y = {
-            let mut y = (data.next().unwrap() - 512).clamp(-511, 511);
-            if y.abs() < 40 { // Check for the deadzone (square with side length `40 * 2` centered at the origin `(0, 0)`)
-                y = 0;
-            }
-            y
-        };
-        // Decode the sensitivity
-        let sensitivity = data.next().unwrap();
-        // Decode the scroll wheel
-        let up = data.next().unwrap() == 1;
-        let down = data.next().unwrap() == 1;
-        let scroll = match (up, down) {
-            (false, false) | (true, true) => 0,
-            (true, false) => 1,
-            (false, true) => -1,
-        };
-
-        // Get the original mouse position
-        let position = mouse.get_position().unwrap();
-        // Move the mouse by the decoded amount
-        mouse
-            .move_to(
-                position.x + x.checked_div(sensitivity).unwrap_or(0),
-                position.y + y.checked_div(sensitivity).unwrap_or(0),
-            )
-            .unwrap();
-        // Click and scroll the mouse
-        if let Some(click) = click {
-            mouse.click(&click).unwrap();
         }
-        mouse.wheel(scroll).unwrap();
+    });
+
+    // On the main thread, let the user send commands back to the
+    // transmitter, e.g. to reboot it into its bootloader or reset its
+    // tuning, without unplugging it
+    let mut options = Commands::ALL.iter().map(|command| command.label()).collect::<Vec<_>>();
+    options.push("quit");
+    loop {
+        let choice = Select::new("send a command", options.clone()).prompt().unwrap();
+        let Some(command) = Commands::ALL.into_iter().find(|command| command.label() == choice)
+        else {
+            break;
+        };
+        command.send(&mut writer).unwrap();
     }
 }
@@ -0,0 +1,129 @@
+/*
+ * Pointer acceleration curve used to turn a decoded axis delta into the
+ * number of pixels the cursor should actually move.
+ *
+ * Replaces the old fixed `x / sensitivity` linear response behind a fixed
+ * `±40` square deadzone with a circular deadzone and a configurable
+ * gain/exponent curve, so the feel can be tuned for different joystick
+ * hardware without recompiling.
+ */
+
+/// A configurable acceleration curve and deadzone for one stick.
+///
+/// `deadzone` and `max_input` are both in raw axis units (the same scale
+/// as the decoded `dx`/`dy`); `gain_x`/`gain_y` let non-square joysticks
+/// use a different top speed per axis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MotionProfile {
+    /// Radius below which input is ignored (a circle, not a square, so
+    /// diagonal motion isn't biased towards the axes)
+    pub deadzone: f64,
+    /// The largest magnitude the transmitter can report on either axis,
+    /// used to normalise `dx`/`dy` to `[0, 1]` before the curve is applied
+    pub max_input: f64,
+    /// Output gain, in pixels, at full deflection on the X axis
+    pub gain_x: f64,
+    /// Output gain, in pixels, at full deflection on the Y axis
+    pub gain_y: f64,
+    /// Curve exponent: `1.0` is linear, greater than `1.0` gives fine
+    /// control near the centre with a faster ramp further out
+    pub exponent: f64,
+}
+
+impl MotionProfile {
+    /// Apply the deadzone and acceleration curve to a raw decoded delta,
+    /// producing the number of pixels to move the cursor by
+    #[must_use]
+    pub fn apply(&self, x: i32, y: i32) -> (i32, i32) {
+        let (xf, yf) = (f64::from(x), f64::from(y));
+        // Circular deadzone: reject the whole vector if it falls inside
+        // the deadzone radius, rather than testing each axis separately,
+        // so diagonal motion isn't biased towards the axes
+        if xf.mul_add(xf, yf * yf) < self.deadzone * self.deadzone {
+            return (0, 0);
+        }
+        (
+            Self::axis(xf, self.max_input, self.gain_x, self.exponent),
+            Self::axis(yf, self.max_input, self.gain_y, self.exponent),
+        )
+    }
+
+    /// Apply the curve to a single axis: normalise to `[0, 1]`, raise to
+    /// `exponent`, scale by `gain`, and restore the original sign
+    #[allow(clippy::cast_possible_truncation)]
+    fn axis(value: f64, max_input: f64, gain: f64, exponent: f64) -> i32 {
+        // `normalized` is clamped to `[0, 1]`, so the result's magnitude is
+        // bounded by `gain`, which is itself a pixel count well within
+        // `i32`'s range; the cast only ever truncates the curve's
+        // fractional pixel, which is fine for a final cursor delta
+        let normalized = (value.abs() / max_input).clamp(0.0, 1.0);
+        (value.signum() * gain * normalized.powf(exponent)) as i32
+    }
+}
+
+impl Default for MotionProfile {
+    /// A profile close to the original behaviour: a deadzone of `40`, a
+    /// linear (`exponent = 1.0`) curve, and equal X/Y gain scaled to
+    /// approximate the old `x / sensitivity` divide at its default
+    /// `sensitivity` of `8`, rather than the unattenuated `gain = max_input`
+    /// full 1:1 response a raw flick would otherwise produce
+    fn default() -> Self {
+        Self {
+            deadzone: 40.0,
+            max_input: 511.0,
+            gain_x: 511.0 / 8.0,
+            gain_y: 511.0 / 8.0,
+            exponent: 1.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MotionProfile;
+
+    fn profile() -> MotionProfile {
+        MotionProfile {
+            deadzone: 10.0,
+            max_input: 100.0,
+            gain_x: 50.0,
+            gain_y: 50.0,
+            exponent: 1.0,
+        }
+    }
+
+    #[test]
+    fn inside_deadzone_is_ignored() {
+        assert_eq!(profile().apply(5, 5), (0, 0));
+    }
+
+    #[test]
+    fn circular_deadzone_allows_diagonal_motion_a_square_would_reject() {
+        // Each axis alone (8) is below the deadzone radius (10), as a
+        // square deadzone would test independently, but the combined
+        // vector magnitude (~11.3) is past it
+        assert_eq!(profile().apply(8, 8), (4, 4));
+    }
+
+    #[test]
+    fn outside_deadzone_is_linear_by_default() {
+        // Half of max_input, linear curve: half of gain, signed
+        assert_eq!(profile().apply(50, -50), (25, -25));
+    }
+
+    #[test]
+    fn exponent_curves_below_linear_response() {
+        let curved = MotionProfile {
+            exponent: 2.0,
+            ..profile()
+        };
+        // Half of max_input squared is a quarter, not a half, of gain
+        assert_eq!(curved.apply(50, 0), (12, 0));
+    }
+
+    #[test]
+    fn input_is_clamped_past_max_input() {
+        let clamped = profile().apply(200, -200);
+        assert_eq!(clamped, (50, -50));
+    }
+}
@@ -0,0 +1,450 @@
+/*
+ * Wire-protocol decoders for the receiver.
+ *
+ * The receiver used to hardcode a single framing (whitespace-separated ASCII
+ * integers terminated by `\r`). `MouseProtocol` pulls that decode step out
+ * behind a trait so the same receiver can also drive real serial-mouse
+ * hardware speaking the classic Microsoft or Mouse Systems wire formats.
+ */
+
+// `bitflags!` is used to model the PS/2-style status byte
+use bitflags::bitflags;
+// `Keys` is reused here so protocol implementations can report clicks using
+// the same type the rest of the program already clicks with
+use mouse_rs::types::keys::Keys;
+
+bitflags! {
+    /// The status byte of a PS/2-style mouse packet.
+    ///
+    /// `ALWAYS_ONE` is always set by a real device; if it's clear, the
+    /// packet framing has slipped and the packet should be dropped rather
+    /// than decoded. The overflow bits signal that an axis moved further
+    /// than the packet can represent, which callers should treat as
+    /// saturation rather than trusting the raw delta byte.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct MouseFlags: u8 {
+        /// Left button held
+        const LEFT_BUTTON = 0x01;
+        /// Right button held
+        const RIGHT_BUTTON = 0x02;
+        /// Middle button held
+        const MIDDLE_BUTTON = 0x04;
+        /// Always set by a real device; clear means we're desynced
+        const ALWAYS_ONE = 0x08;
+        /// Sign bit for the X delta
+        const X_SIGN = 0x10;
+        /// Sign bit for the Y delta
+        const Y_SIGN = 0x20;
+        /// The X delta overflowed what the packet can represent
+        const X_OVERFLOW = 0x40;
+        /// The Y delta overflowed what the packet can represent
+        const Y_OVERFLOW = 0x80;
+    }
+}
+
+/// A single decoded mouse event.
+///
+/// The binary hardware protocols (Microsoft, Mouse Systems) only ever carry
+/// buttons and a relative `dx`/`dy`, so `scroll` is `0` for them; only
+/// [`LineProtocol`] has a scroll wheel to report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MouseEvent {
+    /// Left button held
+    pub left: bool,
+    /// Right button held
+    pub right: bool,
+    /// Middle button held
+    pub middle: bool,
+    /// Relative movement on the X axis
+    pub dx: i32,
+    /// Relative movement on the Y axis
+    pub dy: i32,
+    /// Scroll direction: `1` up, `-1` down, `0` none
+    pub scroll: i32,
+}
+
+impl MouseEvent {
+    /// The button, if any, that should be clicked for this event
+    #[must_use]
+    pub const fn click(&self) -> Option<Keys> {
+        match (self.left, self.right, self.middle) {
+            (false, false, false) => None,
+            (_, _, true) => Some(Keys::MIDDLE),
+            (_, true, _) => Some(Keys::RIGHT),
+            (true, _, _) => Some(Keys::LEFT),
+        }
+    }
+}
+
+/// Something that can turn a stream of serial bytes into [`MouseEvent`]s.
+///
+/// Implementations are fed one byte at a time so they can resynchronise on
+/// their own header/sync marker after a framing error, rather than the
+/// receiver discarding a whole line.
+pub trait MouseProtocol: Send {
+    /// Feed a single byte read from the serial port. Returns `Some` once a
+    /// full packet has been assembled, `None` while still waiting on more
+    /// bytes.
+    fn feed(&mut self, byte: u8) -> Option<MouseEvent>;
+
+    /// A short, human-readable name for use in the protocol picker
+    fn name(&self) -> &'static str;
+}
+
+/// The original line-based format: `\r`-terminated, whitespace-separated
+/// ASCII integers (left click, right click, x, y, sensitivity, scroll up,
+/// scroll down).
+#[derive(Debug, Default)]
+pub struct LineProtocol {
+    /// Bytes accumulated since the last `\r`
+    buffer: Vec<u8>,
+}
+
+impl MouseProtocol for LineProtocol {
+    fn feed(&mut self, byte: u8) -> Option<MouseEvent> {
+        // Keep buffering until we see the line terminator
+        if byte != b'\r' {
+            self.buffer.push(byte);
+            return None;
+        }
+        // Take the line out of the buffer, leaving it empty for the next one
+        let line = std::mem::take(&mut self.buffer);
+        let data = String::from_utf8(line).ok()?;
+        let mut data = data.split_whitespace().map(str::parse::<i32>);
+        // Bail out (and resync on the next line) if parsing fails or there
+        // aren't exactly the 7 numbers we expect
+        let mut next = || data.next()?.ok();
+        let left = next()? == 1;
+        let right = next()? == 1;
+        let x = next()?;
+        let y = next()?;
+        // The transmitter still sends a sensitivity value, but the
+        // deadzone/gain/curve are now handled centrally by a
+        // `MotionProfile`, so it's read (to keep the line's framing) and
+        // discarded
+        let _sensitivity = next()?;
+        let up = next()? == 1;
+        let down = next()? == 1;
+        // A stray extra number means this wasn't a well-formed line
+        if data.next().is_some() {
+            return None;
+        }
+        let scroll = match (up, down) {
+            (false, false) | (true, true) => 0,
+            (true, false) => 1,
+            (false, true) => -1,
+        };
+        // Centre on the origin; the deadzone is applied later by the
+        // active `MotionProfile`
+        let dx = (x - 512).clamp(-511, 511);
+        let dy = (y - 512).clamp(-511, 511);
+        Some(MouseEvent {
+            left,
+            right,
+            middle: false,
+            dx,
+            dy,
+            scroll,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "line (custom ASCII format)"
+    }
+}
+
+/// The classic Microsoft serial-mouse protocol: 3-byte packets.
+///
+/// Byte 0 is the header, identified by its `0x40` sync bit; it carries the
+/// left/right button bits and the high 2 bits of each delta. Bytes 1 and 2
+/// carry the low 6 bits of `dx` and `dy` respectively.
+#[derive(Debug, Default)]
+pub struct MicrosoftProtocol {
+    /// Bytes of the in-progress packet, not including the header
+    bytes: Vec<u8>,
+    /// The header byte, once we've synced on it
+    header: Option<u8>,
+}
+
+impl MouseProtocol for MicrosoftProtocol {
+    fn feed(&mut self, byte: u8) -> Option<MouseEvent> {
+        // Not synced yet: look for the header's sync bit. Bytes that don't
+        // carry it are noise (or we're mid-packet after a dropped byte) and
+        // are discarded one at a time until we find it.
+        if self.header.is_none() {
+            if byte & 0x40 != 0 {
+                self.header = Some(byte);
+            }
+            return None;
+        }
+        self.bytes.push(byte);
+        if self.bytes.len() < 2 {
+            return None;
+        }
+        // Full packet assembled: decode it and reset to wait for the next
+        // header, whether or not we successfully synced the packet
+        let header = self.header.take().unwrap();
+        let [low, high] = [self.bytes[0], self.bytes[1]];
+        self.bytes.clear();
+
+        let left = header & 0x20 != 0;
+        let right = header & 0x10 != 0;
+        // Reassemble dx/dy as signed 8-bit values from the header's high 2
+        // bits and the low 6 bits in each data byte. `cast_signed` just
+        // reinterprets the bit pattern (the value is meant to wrap), so
+        // it's used instead of `as i8`, which clippy flags as a possibly
+        // lossy cast
+        let dx = (((header & 0x03) << 6) | (low & 0x3F)).cast_signed();
+        let dy = (((header & 0x0C) << 4) | (high & 0x3F)).cast_signed();
+
+        Some(MouseEvent {
+            left,
+            right,
+            middle: false,
+            dx: i32::from(dx),
+            dy: i32::from(dy),
+            scroll: 0,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "Microsoft serial mouse"
+    }
+}
+
+/// The classic Mouse Systems serial-mouse protocol: 5-byte packets.
+///
+/// The header byte is `0x80` with the low 3 bits holding *inverted* button
+/// state, followed by `dx1`, `dy1`, `dx2`, `dy2` as signed bytes; the
+/// reported delta for each axis is the sum of its two samples.
+#[derive(Debug, Default)]
+pub struct MousesystemsProtocol {
+    /// Bytes of the in-progress packet, not including the header
+    bytes: Vec<u8>,
+    /// The header byte, once we've synced on it
+    header: Option<u8>,
+}
+
+impl MouseProtocol for MousesystemsProtocol {
+    fn feed(&mut self, byte: u8) -> Option<MouseEvent> {
+        if self.header.is_none() {
+            // A header is `0x80` plus the 3 inverted button bits, so its top
+            // 5 bits are always `10000`; any other byte (including a
+            // negative data byte, whose top bit is also set) is discarded
+            if byte & 0xF8 == 0x80 {
+                self.header = Some(byte);
+            }
+            return None;
+        }
+        self.bytes.push(byte);
+        if self.bytes.len() < 4 {
+            return None;
+        }
+        let header = self.header.take().unwrap();
+        let [dx1, dy1, dx2, dy2] = [self.bytes[0], self.bytes[1], self.bytes[2], self.bytes[3]];
+        self.bytes.clear();
+
+        // Button bits are active-low
+        let left = header & 0x04 == 0;
+        let middle = header & 0x02 == 0;
+        let right = header & 0x01 == 0;
+
+        // `cast_signed` reinterprets the bit pattern rather than narrowing,
+        // so it's used in place of `as i8` here too
+        let dx = i32::from(dx1.cast_signed()) + i32::from(dx2.cast_signed());
+        let dy = i32::from(dy1.cast_signed()) + i32::from(dy2.cast_signed());
+
+        Some(MouseEvent {
+            left,
+            right,
+            middle,
+            dx,
+            dy,
+            scroll: 0,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "Mouse Systems serial mouse"
+    }
+}
+
+/// The PS/2 mouse protocol: 3-byte packets made of a [`MouseFlags`] status
+/// byte followed by the low 8 bits of `dx` and of `dy`.
+///
+/// Sign and overflow for each axis are carried in the status byte rather
+/// than in the delta itself, since a plain signed byte can't represent a
+/// 9-bit delta.
+#[derive(Debug, Default)]
+pub struct Ps2Protocol {
+    /// Bytes of the in-progress packet, not including the status byte
+    bytes: Vec<u8>,
+    /// The status byte, once we've synced on it
+    status: Option<MouseFlags>,
+}
+
+impl Ps2Protocol {
+    /// Reassemble a signed 9-bit (two's complement) delta from its low 8
+    /// bits and sign/overflow flags, saturating to `±255` on overflow
+    fn axis(magnitude: u8, sign: bool, overflow: bool) -> i32 {
+        if overflow {
+            return if sign { -255 } else { 255 };
+        }
+        if sign {
+            i32::from(magnitude) - 256
+        } else {
+            i32::from(magnitude)
+        }
+    }
+}
+
+impl MouseProtocol for Ps2Protocol {
+    fn feed(&mut self, byte: u8) -> Option<MouseEvent> {
+        if self.status.is_none() {
+            let flags = MouseFlags::from_bits_truncate(byte);
+            // A real device always sets `ALWAYS_ONE`; if it's clear we're
+            // still desynced and this byte is noise to be discarded
+            if flags.contains(MouseFlags::ALWAYS_ONE) {
+                self.status = Some(flags);
+            }
+            return None;
+        }
+        self.bytes.push(byte);
+        if self.bytes.len() < 2 {
+            return None;
+        }
+        let status = self.status.take().unwrap();
+        let [dx, dy] = [self.bytes[0], self.bytes[1]];
+        self.bytes.clear();
+
+        Some(MouseEvent {
+            left: status.contains(MouseFlags::LEFT_BUTTON),
+            right: status.contains(MouseFlags::RIGHT_BUTTON),
+            middle: status.contains(MouseFlags::MIDDLE_BUTTON),
+            dx: Self::axis(
+                dx,
+                status.contains(MouseFlags::X_SIGN),
+                status.contains(MouseFlags::X_OVERFLOW),
+            ),
+            dy: Self::axis(
+                dy,
+                status.contains(MouseFlags::Y_SIGN),
+                status.contains(MouseFlags::Y_OVERFLOW),
+            ),
+            scroll: 0,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "PS/2 mouse"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MicrosoftProtocol, MouseEvent, MouseProtocol, MousesystemsProtocol, Ps2Protocol};
+
+    /// Feed every byte of `packet` and return the event the last one
+    /// assembles, panicking if the packet didn't decode
+    fn feed_packet(protocol: &mut impl MouseProtocol, packet: &[u8]) -> MouseEvent {
+        let (&last, rest) = packet.split_last().unwrap();
+        for &byte in rest {
+            assert!(protocol.feed(byte).is_none(), "packet decoded too early");
+        }
+        protocol.feed(last).expect("packet should have decoded")
+    }
+
+    #[test]
+    fn microsoft_decodes_buttons_and_signed_deltas() {
+        // header: sync (0x40) | left (0x20) | dy high bits (0x0C); dx = 5,
+        // dy = -3
+        let event = feed_packet(&mut MicrosoftProtocol::default(), &[0x6C, 0x05, 0x3D]);
+        assert_eq!(
+            event,
+            MouseEvent {
+                left: true,
+                right: false,
+                middle: false,
+                dx: 5,
+                dy: -3,
+                scroll: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn microsoft_resyncs_after_a_garbage_byte() {
+        let mut protocol = MicrosoftProtocol::default();
+        // A byte without the sync bit set is noise and should be dropped
+        // on its own, not throw off the next real packet
+        assert!(protocol.feed(0x00).is_none());
+        let event = feed_packet(&mut protocol, &[0x6C, 0x05, 0x3D]);
+        assert_eq!(event.dx, 5);
+        assert_eq!(event.dy, -3);
+    }
+
+    #[test]
+    fn mousesystems_sums_the_two_samples_per_axis() {
+        // header: sync (0x80), all buttons released (active-low, so all
+        // three low bits set); dx = 3 + 2 = 5, dy = 4 + (-1) = 3
+        let event = feed_packet(
+            &mut MousesystemsProtocol::default(),
+            &[0x87, 0x03, 0x04, 0x02, 0xFF],
+        );
+        assert_eq!(
+            event,
+            MouseEvent {
+                left: false,
+                right: false,
+                middle: false,
+                dx: 5,
+                dy: 3,
+                scroll: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn mousesystems_resyncs_after_a_garbage_byte() {
+        let mut protocol = MousesystemsProtocol::default();
+        // A byte without the sync bit set is noise
+        assert!(protocol.feed(0x00).is_none());
+        let event = feed_packet(&mut protocol, &[0x87, 0x03, 0x04, 0x02, 0xFF]);
+        assert_eq!((event.dx, event.dy), (5, 3));
+    }
+
+    #[test]
+    fn ps2_decodes_sign_bits_into_negative_deltas() {
+        // status: ALWAYS_ONE | LEFT_BUTTON | X_SIGN; with its sign bit set,
+        // dx's low byte 5 is the 9-bit value 5 - 256, and dy is plain 7
+        let event = feed_packet(&mut Ps2Protocol::default(), &[0x19, 0x05, 0x07]);
+        assert_eq!(
+            event,
+            MouseEvent {
+                left: true,
+                right: false,
+                middle: false,
+                dx: 5 - 256,
+                dy: 7,
+                scroll: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn ps2_overflow_saturates_instead_of_trusting_the_magnitude_byte() {
+        // status: ALWAYS_ONE | X_OVERFLOW | X_SIGN; the dx magnitude byte
+        // (0x00) should be ignored in favour of the saturated value
+        let event = feed_packet(&mut Ps2Protocol::default(), &[0x58, 0x00, 0x03]);
+        assert_eq!((event.dx, event.dy), (-255, 3));
+    }
+
+    #[test]
+    fn ps2_resyncs_after_a_status_byte_with_always_one_clear() {
+        let mut protocol = Ps2Protocol::default();
+        // ALWAYS_ONE clear means this isn't a real status byte
+        assert!(protocol.feed(0x00).is_none());
+        let event = feed_packet(&mut protocol, &[0x19, 0x05, 0x07]);
+        assert_eq!((event.dx, event.dy), (5 - 256, 7));
+    }
+}